@@ -1,10 +1,11 @@
 #![feature(trivial_bounds)]
 
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashSet},
     env::current_dir,
     io::Read,
-    time::{Duration, Instant},
+    sync::OnceLock,
+    time::{Duration, Instant, UNIX_EPOCH},
 };
 
 use anyhow::Result;
@@ -12,10 +13,22 @@ use sha2::{Digest, Sha256};
 use turbo_tasks::{unit, util::FormatDuration, TurboTasks, UpdateInfo, Vc};
 use turbo_tasks_fs::{
     register, DirectoryContent, DirectoryEntry, DiskFileSystem, FileContent, FileSystem,
-    FileSystemPath,
+    FileSystemPath, HashCache, RcStr, TraversalMode,
 };
+#[cfg(unix)]
+use turbo_tasks_fs::OpenDir;
 use turbo_tasks_memory::MemoryBackend;
 
+/// Cold-start digest cache, keyed by file metadata rather than content, so a
+/// rerun of this example only re-hashes files that actually changed since
+/// the last run. Opened once at the start of the root task in `main`, before
+/// any hashing task runs.
+static HASH_CACHE: OnceLock<HashCache> = OnceLock::new();
+
+fn hash_cache() -> &'static HashCache {
+    HASH_CACHE.get().expect("hash cache not initialized")
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     register();
@@ -30,6 +43,9 @@ async fn main() -> Result<()> {
     let task = tt.spawn_root_task(|| {
         Box::pin(async {
             let root = current_dir().unwrap().to_str().unwrap().to_string();
+            HASH_CACHE
+                .set(HashCache::open(format!("{root}/.turbo-hash-cache"))?)
+                .ok();
             let disk_fs = DiskFileSystem::new("project".to_string(), root);
             disk_fs.await?.start_watching()?;
 
@@ -44,6 +60,14 @@ async fn main() -> Result<()> {
     tt.wait_task_completion(task, true).await.unwrap();
     println!("done in {}", FormatDuration(start.elapsed()));
 
+    let demo_dir = current_dir().unwrap().join("demo");
+    #[cfg(unix)]
+    let traversal_mode = TraversalMode::OpenAt;
+    #[cfg(not(unix))]
+    let traversal_mode = TraversalMode::AbsolutePaths;
+    hash_cache().gc(&live_paths(&demo_dir, traversal_mode));
+    hash_cache().flush()?;
+
     loop {
         let UpdateInfo {
             duration, tasks, ..
@@ -55,17 +79,70 @@ async fn main() -> Result<()> {
 }
 
 #[turbo_tasks::function]
-async fn print_hash(dir_hash: Vc<String>) -> Result<Vc<()>> {
-    println!("DIR HASH: {}", dir_hash.await?.as_str());
+async fn print_hash(dir_hash: Vc<RcStr>) -> Result<Vc<()>> {
+    println!("DIR HASH: {}", &*dir_hash.await?);
     Ok(unit())
 }
 
-async fn filename(path: Vc<FileSystemPath>) -> Result<String> {
-    Ok(path.await?.path.split('/').last().unwrap().to_string())
+/// Walks `dir` and returns every file path relative to the current
+/// directory, for use as the live set `HashCache::gc` keeps.
+///
+/// Under `TraversalMode::OpenAt` this recurses by keeping each directory's
+/// open fd and resolving its children with it (`OpenDir::read_entries`,
+/// `OpenDir::open_subdir`) instead of re-walking an absolute path per level;
+/// `abs_dir` is threaded through purely to build the relative-path strings
+/// `HashCache` keys on, not to re-resolve anything.
+fn live_paths(dir: &std::path::Path, mode: TraversalMode) -> HashSet<String> {
+    let mut paths = HashSet::new();
+    #[cfg(unix)]
+    if mode == TraversalMode::OpenAt {
+        if let Ok(root) = OpenDir::open(None, dir) {
+            walk_openat(&root, dir, &mut paths);
+            return paths;
+        }
+    }
+    let _ = mode;
+    walk_absolute(dir, &mut paths);
+    paths
+}
+
+fn walk_absolute(dir: &std::path::Path, paths: &mut HashSet<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            walk_absolute(&path, paths);
+        } else if let Ok(relative) = path.strip_prefix(current_dir().unwrap()) {
+            paths.insert(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+}
+
+#[cfg(unix)]
+fn walk_openat(handle: &OpenDir, abs_dir: &std::path::Path, paths: &mut HashSet<String>) {
+    let Ok(entries) = handle.read_entries() else {
+        return;
+    };
+    for entry in entries {
+        let abs_child = abs_dir.join(&entry.name);
+        if entry.is_dir {
+            if let Ok(child) = handle.open_subdir(&entry.name) {
+                walk_openat(&child, &abs_child, paths);
+            }
+        } else if let Ok(relative) = abs_child.strip_prefix(current_dir().unwrap()) {
+            paths.insert(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+}
+
+async fn filename(path: Vc<FileSystemPath>) -> Result<RcStr> {
+    Ok(RcStr::from(path.await?.path.split('/').last().unwrap()))
 }
 
 #[turbo_tasks::function]
-async fn hash_directory(directory: Vc<FileSystemPath>) -> Result<Vc<String>> {
+async fn hash_directory(directory: Vc<FileSystemPath>) -> Result<Vc<RcStr>> {
     let dir_path = &directory.await?.path;
     let content = directory.read_dir();
     let mut hashes = BTreeMap::new();
@@ -89,30 +166,78 @@ async fn hash_directory(directory: Vc<FileSystemPath>) -> Result<Vc<String>> {
             println!("{}: not found", directory.await?.path);
         }
     };
-    let hash = hash_content(
-        &mut hashes
-            .into_values()
-            .collect::<Vec<String>>()
-            .join(",")
-            .as_bytes(),
-    );
+    let children_key = hashes
+        .values()
+        .map(RcStr::as_ref)
+        .collect::<Vec<&str>>()
+        .join(",");
+    let hash = match hash_cache().get_dir(&children_key) {
+        Some(digest) => Vc::cell(digest),
+        None => {
+            let hash = hash_content(&mut children_key.as_bytes());
+            hash_cache().put_dir(children_key, hash.await?.clone_value());
+            hash
+        }
+    };
     println!("hash_directory({})", dir_path);
     Ok(hash)
 }
 
 #[turbo_tasks::function]
-async fn hash_file(file_path: Vc<FileSystemPath>) -> Result<Vc<String>> {
+async fn hash_file(file_path: Vc<FileSystemPath>) -> Result<Vc<RcStr>> {
+    let path = &file_path.await?.path;
+    let metadata = std::fs::metadata(current_dir()?.join(path)).ok();
+    if let Some(metadata) = &metadata {
+        let (mtime, size, len) = file_metadata_key(metadata)?;
+        if let Some(digest) = hash_cache().get_file(path, mtime, size, len) {
+            return Ok(Vc::cell(digest));
+        }
+    }
+
     let content = file_path.read().await?;
-    Ok(match &*content {
+    let hash = match &*content {
         FileContent::Content(file) => hash_content(&mut file.read()),
         FileContent::NotFound => {
             // report error
-            Vc::cell("".to_string())
+            Vc::cell(RcStr::from(""))
         }
-    })
+    };
+
+    if let Some(metadata) = &metadata {
+        let (mtime, size, len) = file_metadata_key(metadata)?;
+        hash_cache().put_file(path, mtime, size, len, hash.await?.clone_value());
+    }
+    Ok(hash)
+}
+
+/// Returns the `(mtime, size, len)` triple `hash_file` uses as a cheap
+/// stand-in for "the file's bytes haven't changed since we last hashed it".
+///
+/// `mtime` is sub-second (nanoseconds since the epoch): truncating to whole
+/// seconds would let a file rewritten twice within the same second at the
+/// same size read back a stale cached digest. On Unix, `len` is the file's
+/// allocated block count rather than a repeat of `size` (elsewhere it is
+/// `size` again) — on sparse or just-extended
+/// files the logical size can lag behind what's actually been written, so
+/// the block count is a second, independently-derived signal from the same
+/// `stat` call rather than dead weight.
+fn file_metadata_key(metadata: &std::fs::Metadata) -> Result<(u64, u64, u64)> {
+    let mtime = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)?
+        .as_nanos() as u64;
+    let size = metadata.len();
+    #[cfg(unix)]
+    let len = {
+        use std::os::unix::fs::MetadataExt;
+        metadata.blocks()
+    };
+    #[cfg(not(unix))]
+    let len = size;
+    Ok((mtime, size, len))
 }
 
-fn hash_content<R: Read>(content: &mut R) -> Vc<String> {
+fn hash_content<R: Read>(content: &mut R) -> Vc<RcStr> {
     let mut hasher = Sha256::new();
     let mut buf = [0; 1024];
     while let Ok(size) = content.read(&mut buf) {
@@ -120,5 +245,5 @@ fn hash_content<R: Read>(content: &mut R) -> Vc<String> {
     }
     let result = format!("{:x}", hasher.finalize());
 
-    Vc::cell(result)
+    Vc::cell(RcStr::from(result))
 }
@@ -0,0 +1,251 @@
+//! Content-defined chunking and a deduplicated, content-addressed chunk
+//! store.
+//!
+//! Files are split into variable-size chunks using a gear-hash rolling
+//! fingerprint rather than fixed-size blocks, so inserting or removing a few
+//! bytes in the middle of a file only changes the chunks touching the edit
+//! instead of reshuffling every chunk boundary after it. Each chunk is
+//! content-addressed by the SHA-256 digest of its bytes and, thanks to
+//! `turbo_tasks` memoizing `insert_chunk` by argument, stored at most once
+//! regardless of how many files (or how many places in the same file)
+//! contain it.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use turbo_tasks::Vc;
+
+use crate::{FileContent, FileSystemPath, RcStr};
+
+/// Minimum chunk size in bytes (2 KiB). Prevents pathological inputs (e.g.
+/// long runs of zeroes) from producing a storm of tiny chunks.
+const MIN_CHUNK_SIZE: usize = 2 * 1024;
+/// Maximum chunk size in bytes (64 KiB). A boundary is forced here even if
+/// the fingerprint never hits the mask, so incompressible data can't grow a
+/// single chunk unboundedly.
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+/// Number of low bits of the fingerprint that must be zero to declare a
+/// boundary. A 14-bit mask targets an average chunk size of 2^14 = 16 KiB.
+const BOUNDARY_MASK: u64 = (1 << 14) - 1;
+
+/// Per-byte mixing constants for the gear hash, generated by a fixed
+/// compile-time PRNG so chunk boundaries are stable across builds. Shifting
+/// the accumulator left by one bit per byte naturally ages old bytes out of
+/// the fingerprint after 64 of them, which gives the rolling hash a 64-byte
+/// effective window without needing to track one explicitly.
+static GEAR_TABLE: [u64; 256] = build_gear_table();
+
+const fn build_gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut seed: u64 = 0x9E3779B97F4A7C15;
+    let mut i = 0;
+    while i < 256 {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        table[i] = seed;
+        i += 1;
+    }
+    table
+}
+
+/// Splits `content` into variable-size chunks at gear-hash boundaries.
+/// Always returns at least one (possibly empty) chunk, and always ends a
+/// chunk at EOF even if no boundary was found there.
+fn chunk_boundaries(content: &[u8]) -> Vec<(usize, usize)> {
+    if content.is_empty() {
+        return vec![(0, 0)];
+    }
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut fingerprint: u64 = 0;
+    for i in 0..content.len() {
+        fingerprint = (fingerprint << 1).wrapping_add(GEAR_TABLE[content[i] as usize]);
+        let len = i + 1 - start;
+        if len >= MIN_CHUNK_SIZE && (fingerprint & BOUNDARY_MASK == 0 || len >= MAX_CHUNK_SIZE) {
+            chunks.push((start, i + 1));
+            start = i + 1;
+            fingerprint = 0;
+        }
+    }
+    if start < content.len() {
+        chunks.push((start, content.len()));
+    }
+    chunks
+}
+
+fn hash_chunk(data: &[u8]) -> RcStr {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    RcStr::from(format!("{:x}", hasher.finalize()))
+}
+
+/// A single chunk of file content, addressed by the SHA-256 digest of its
+/// bytes.
+#[turbo_tasks::value]
+pub struct Chunk {
+    pub digest: RcStr,
+    pub data: Vec<u8>,
+}
+
+/// A file represented as the ordered list of its chunk digests. Two files
+/// (or two regions of the same file) that share identical bytes share the
+/// corresponding entries, so the digest list is also a cheap way to compute
+/// a dedup ratio across a tree.
+#[turbo_tasks::value]
+pub struct ChunkedFile {
+    pub chunk_digests: Vec<RcStr>,
+}
+
+/// A content-addressed, deduplicating store of file chunks.
+///
+/// Storing is a `turbo_tasks` function, so re-chunking a [`FileContent`]
+/// that hasn't changed is a cache hit rather than a re-hash, and inserting a
+/// chunk whose digest has already been seen anywhere else reuses the
+/// existing cell instead of allocating a new one.
+#[turbo_tasks::value(shared)]
+pub struct ContentStore;
+
+#[turbo_tasks::value_impl]
+impl ContentStore {
+    #[turbo_tasks::function]
+    pub fn new() -> Vc<Self> {
+        ContentStore.cell()
+    }
+
+    /// Chunks `content` and inserts each unique chunk into the store,
+    /// returning the file as an ordered list of chunk digests.
+    #[turbo_tasks::function]
+    pub async fn store(self: Vc<Self>, content: Vc<FileContent>) -> Result<Vc<ChunkedFile>> {
+        let chunk_digests = match &*content.await? {
+            FileContent::Content(file) => {
+                let bytes = file.content().to_bytes()?;
+                chunk_boundaries(&bytes)
+                    .into_iter()
+                    .map(|(start, end)| {
+                        let data = bytes[start..end].to_vec();
+                        let digest = hash_chunk(&data);
+                        insert_chunk(digest.clone(), data);
+                        digest
+                    })
+                    .collect()
+            }
+            FileContent::NotFound => Vec::new(),
+        };
+        Ok(ChunkedFile { chunk_digests }.cell())
+    }
+
+    /// Looks up a previously inserted chunk by its digest. Returns an empty
+    /// chunk if the digest was never inserted in this process (the registry
+    /// backing the lookup is in memory only).
+    #[turbo_tasks::function]
+    pub fn get(self: Vc<Self>, digest: RcStr) -> Vc<Chunk> {
+        if let Some(chunk) = registry().lock().unwrap().get(&digest) {
+            return *chunk;
+        }
+        Chunk {
+            digest,
+            data: Vec::new(),
+        }
+        .cell()
+    }
+}
+
+/// Chunks the file at `path` and returns its ordered list of chunk digests,
+/// the crate's public entry point for turning a tracked file into
+/// content-addressed chunks without callers needing to hold a `Vc<ContentStore>`
+/// themselves.
+#[turbo_tasks::function]
+pub async fn chunk_digests(path: Vc<FileSystemPath>) -> Result<Vc<Vec<RcStr>>> {
+    let content = path.read();
+    let chunked = ContentStore::new().store(content).await?;
+    Ok(Vc::cell(chunked.chunk_digests.clone()))
+}
+
+/// Registry backing [`ContentStore::get`]. `insert_chunk` populates it as a
+/// side effect of inserting, so every chunk handed out by `store` stays
+/// reachable by digest instead of being dropped the moment `store`'s local
+/// variable goes out of scope.
+fn registry() -> &'static Mutex<HashMap<RcStr, Vc<Chunk>>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<RcStr, Vc<Chunk>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Inserts a chunk keyed by its digest. `turbo_tasks` memoizes this by
+/// argument value, so calling it twice with the same digest (from the same
+/// file re-chunked, or from an unrelated file that happens to share a
+/// region) returns the same cached cell without storing the bytes twice.
+/// The cell is also recorded in [`registry`] so it can be retrieved later
+/// through [`ContentStore::get`] by digest alone.
+#[turbo_tasks::function]
+fn insert_chunk(digest: RcStr, data: Vec<u8>) -> Vc<Chunk> {
+    let chunk = Chunk {
+        digest: digest.clone(),
+        data,
+    }
+    .cell();
+    registry().lock().unwrap().entry(digest).or_insert(chunk);
+    chunk
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_input_is_a_single_empty_chunk() {
+        assert_eq!(chunk_boundaries(&[]), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn always_cuts_a_chunk_at_eof() {
+        let data = vec![0u8; MIN_CHUNK_SIZE + 10];
+        let chunks = chunk_boundaries(&data);
+        assert_eq!(chunks.last().unwrap().1, data.len());
+    }
+
+    #[test]
+    fn never_produces_a_chunk_below_the_minimum_size() {
+        // Incompressible data maximizes the chance of hitting the boundary
+        // mask early; every chunk but possibly the last must still respect
+        // MIN_CHUNK_SIZE.
+        let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+        let data: Vec<u8> = (0..(MAX_CHUNK_SIZE * 4))
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                (seed >> 24) as u8
+            })
+            .collect();
+        let chunks = chunk_boundaries(&data);
+        for &(start, end) in &chunks[..chunks.len() - 1] {
+            assert!(end - start >= MIN_CHUNK_SIZE);
+        }
+    }
+
+    #[test]
+    fn never_produces_a_chunk_above_the_maximum_size() {
+        let data: Vec<u8> = (0..(MAX_CHUNK_SIZE * 4)).map(|i| (i % 251) as u8).collect();
+        let chunks = chunk_boundaries(&data);
+        for &(start, end) in &chunks {
+            assert!(end - start <= MAX_CHUNK_SIZE);
+        }
+    }
+
+    #[test]
+    fn boundaries_are_stable_for_unchanged_prefixes() {
+        // Editing the tail of the input must not move the boundaries that
+        // fall entirely within the unchanged prefix.
+        let mut data: Vec<u8> = (0..(MAX_CHUNK_SIZE * 2)).map(|i| (i % 251) as u8).collect();
+        let before = chunk_boundaries(&data);
+        data.truncate(data.len() - 37);
+        let after = chunk_boundaries(&data);
+        let prefix_chunks = before.len() - 1;
+        assert_eq!(&before[..prefix_chunks], &after[..prefix_chunks]);
+    }
+}
@@ -0,0 +1,225 @@
+//! A disk-backed cache for the digests the `hash_directory` example (and
+//! anything built the same way) computes, so a cold process start doesn't
+//! have to re-read and re-hash every byte of a tree that hasn't changed
+//! since the last run.
+//!
+//! Two kinds of entries are cached, both keyed by something cheaper to
+//! compute than a hash:
+//! - a file's digest, keyed by `(path, mtime, size, len)` — if none of those
+//!   changed since the cached entry was written, the file's bytes are
+//!   assumed unchanged and the cached digest is reused;
+//! - a directory's digest, keyed by its child digests in child-name order
+//!   (the order they are hashed in) — reusing a
+//!   digest here costs nothing once the children's digests are known, since
+//!   it's pure hashing of already-computed strings.
+//!
+//! The cache lives as a single JSON file per kind in a state directory and
+//! is written back with the usual temp-file-then-rename trick so a crash
+//! mid-write can't leave a half-written cache behind.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::RcStr;
+
+const FILE_CACHE_NAME: &str = "file_hashes.json";
+const DIR_CACHE_NAME: &str = "dir_hashes.json";
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+struct FileCacheEntry {
+    mtime: u64,
+    size: u64,
+    len: u64,
+    digest: RcStr,
+}
+
+/// A disk-backed cache of file and directory digests, keyed by metadata
+/// that's far cheaper to read than the file content is to hash.
+pub struct HashCache {
+    state_dir: PathBuf,
+    files: Mutex<HashMap<String, FileCacheEntry>>,
+    dirs: Mutex<HashMap<String, RcStr>>,
+}
+
+impl HashCache {
+    /// Opens (or creates) a hash cache backed by `state_dir`, loading any
+    /// entries persisted by a previous run.
+    pub fn open(state_dir: impl Into<PathBuf>) -> Result<Self> {
+        let state_dir = state_dir.into();
+        fs::create_dir_all(&state_dir)
+            .with_context(|| format!("creating hash cache dir {}", state_dir.display()))?;
+        let files = read_json(&state_dir.join(FILE_CACHE_NAME))?.unwrap_or_default();
+        let dirs = read_json(&state_dir.join(DIR_CACHE_NAME))?.unwrap_or_default();
+        Ok(Self {
+            state_dir,
+            files: Mutex::new(files),
+            dirs: Mutex::new(dirs),
+        })
+    }
+
+    /// Returns the cached digest for `path` if its `mtime`, `size`, and `len`
+    /// all still match the cached entry.
+    pub fn get_file(&self, path: &str, mtime: u64, size: u64, len: u64) -> Option<RcStr> {
+        let files = self.files.lock().unwrap();
+        let entry = files.get(path)?;
+        if entry.mtime == mtime && entry.size == size && entry.len == len {
+            Some(entry.digest.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Records (or replaces) the digest for `path`, along with the metadata
+    /// it was computed from.
+    pub fn put_file(&self, path: &str, mtime: u64, size: u64, len: u64, digest: RcStr) {
+        self.files.lock().unwrap().insert(
+            path.to_string(),
+            FileCacheEntry {
+                mtime,
+                size,
+                len,
+                digest,
+            },
+        );
+    }
+
+    /// Returns the cached digest for a directory whose children's digests,
+    /// in child-name order, joined with `,`, match `children_key`.
+    pub fn get_dir(&self, children_key: &str) -> Option<RcStr> {
+        self.dirs.lock().unwrap().get(children_key).cloned()
+    }
+
+    /// Records (or replaces) the digest for a directory keyed by its joined
+    /// child digests, in child-name order.
+    pub fn put_dir(&self, children_key: String, digest: RcStr) {
+        self.dirs.lock().unwrap().insert(children_key, digest);
+    }
+
+    /// Drops every file entry whose path is not in `live_paths`, so paths
+    /// that were deleted or renamed don't accumulate in the cache forever.
+    /// Directory entries need no equivalent pass: they're keyed by content,
+    /// not by path, and simply stop being looked up once nothing references
+    /// that set of child digests.
+    pub fn gc(&self, live_paths: &std::collections::HashSet<String>) {
+        self.files
+            .lock()
+            .unwrap()
+            .retain(|path, _| live_paths.contains(path));
+    }
+
+    /// Persists both caches to `state_dir`, atomically per file.
+    pub fn flush(&self) -> Result<()> {
+        write_json_atomic(
+            &self.state_dir.join(FILE_CACHE_NAME),
+            &*self.files.lock().unwrap(),
+        )?;
+        write_json_atomic(
+            &self.state_dir.join(DIR_CACHE_NAME),
+            &*self.dirs.lock().unwrap(),
+        )?;
+        Ok(())
+    }
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Option<T>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(
+            serde_json::from_slice(&bytes)
+                .with_context(|| format!("parsing hash cache file {}", path.display()))?,
+        )),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("reading hash cache file {}", path.display())),
+    }
+}
+
+fn write_json_atomic<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    let data = serde_json::to_vec(value)?;
+    fs::write(&tmp_path, data)
+        .with_context(|| format!("writing hash cache tmp file {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("renaming hash cache tmp file into {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn temp_state_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "turbo-tasks-fs-hash-cache-test-{name}-{:?}",
+            std::thread::current().id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn put_then_get_file_round_trips_through_a_flush_and_reopen() {
+        let state_dir = temp_state_dir("file-round-trip");
+        let cache = HashCache::open(&state_dir).unwrap();
+        cache.put_file("a.txt", 1, 2, 3, RcStr::from("digest-a"));
+        cache.flush().unwrap();
+
+        let reopened = HashCache::open(&state_dir).unwrap();
+        assert_eq!(
+            reopened.get_file("a.txt", 1, 2, 3),
+            Some(RcStr::from("digest-a"))
+        );
+
+        fs::remove_dir_all(&state_dir).unwrap();
+    }
+
+    #[test]
+    fn get_file_misses_if_any_key_field_changed() {
+        let cache = HashCache::open(temp_state_dir("file-miss")).unwrap();
+        cache.put_file("a.txt", 1, 2, 3, RcStr::from("digest-a"));
+
+        assert_eq!(cache.get_file("a.txt", 1, 2, 3), Some(RcStr::from("digest-a")));
+        assert_eq!(cache.get_file("a.txt", 9, 2, 3), None);
+        assert_eq!(cache.get_file("a.txt", 1, 9, 3), None);
+        assert_eq!(cache.get_file("a.txt", 1, 2, 9), None);
+    }
+
+    #[test]
+    fn put_then_get_dir_round_trips_through_a_flush_and_reopen() {
+        let state_dir = temp_state_dir("dir-round-trip");
+        let cache = HashCache::open(&state_dir).unwrap();
+        cache.put_dir("digest-a,digest-b".to_string(), RcStr::from("digest-dir"));
+        cache.flush().unwrap();
+
+        let reopened = HashCache::open(&state_dir).unwrap();
+        assert_eq!(
+            reopened.get_dir("digest-a,digest-b"),
+            Some(RcStr::from("digest-dir"))
+        );
+
+        fs::remove_dir_all(&state_dir).unwrap();
+    }
+
+    #[test]
+    fn gc_drops_only_entries_missing_from_the_live_set() {
+        let cache = HashCache::open(temp_state_dir("gc")).unwrap();
+        cache.put_file("keep.txt", 1, 2, 3, RcStr::from("digest-keep"));
+        cache.put_file("gone.txt", 1, 2, 3, RcStr::from("digest-gone"));
+
+        let live: HashSet<String> = ["keep.txt".to_string()].into_iter().collect();
+        cache.gc(&live);
+
+        assert_eq!(
+            cache.get_file("keep.txt", 1, 2, 3),
+            Some(RcStr::from("digest-keep"))
+        );
+        assert_eq!(cache.get_file("gone.txt", 1, 2, 3), None);
+    }
+}
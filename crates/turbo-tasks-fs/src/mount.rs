@@ -0,0 +1,313 @@
+//! Mounts any [`FileSystem`] implementor as a real FUSE mountpoint, so that
+//! ordinary OS tools can browse a computed or virtual tree the same way they
+//! browse a directory on disk.
+//!
+//! The mount is backed entirely by the crate's existing cached `read_dir`/
+//! `read` task functions, so it reflects invalidations the normal way: once
+//! `DiskFileSystem::start_watching()` (or any other invalidator) marks a
+//! path dirty, the next FUSE callback for that path re-runs the task and
+//! sees the new content. No remount is needed.
+//!
+//! Linux-only: FUSE itself is a Linux (and some BSD, via a different crate)
+//! kernel feature, and this module is gated behind the `fuse` feature so
+//! platforms and builds that don't need it pay nothing for it.
+
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use fuser::{
+    FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry,
+    ReplyOpen, Request,
+};
+use tokio::runtime::Handle;
+
+use crate::{DirectoryContent, DirectoryEntry, FileContent, FileSystemPath};
+
+const TTL: Duration = Duration::from_secs(1);
+const ROOT_INODE: u64 = 1;
+
+/// Assigns stable `u64` inodes to [`FileSystemPath`] values and caches the
+/// forward (path -> inode) and reverse (inode -> path) mappings. Inodes are
+/// handed out once and never reused for the lifetime of the mount, matching
+/// the FUSE expectation that an inode keeps denoting "the same file" even
+/// after attribute changes.
+#[derive(Default)]
+struct InodeTracker {
+    next_inode: u64,
+    path_to_inode: HashMap<FileSystemPath, u64>,
+    inode_to_path: HashMap<u64, FileSystemPath>,
+    parent_of: HashMap<u64, u64>,
+    kind_of: HashMap<u64, FileType>,
+}
+
+impl InodeTracker {
+    fn new(root: FileSystemPath) -> Self {
+        let mut tracker = InodeTracker {
+            next_inode: ROOT_INODE + 1,
+            path_to_inode: HashMap::new(),
+            inode_to_path: HashMap::new(),
+            parent_of: HashMap::new(),
+            kind_of: HashMap::new(),
+        };
+        tracker.path_to_inode.insert(root.clone(), ROOT_INODE);
+        tracker.inode_to_path.insert(ROOT_INODE, root);
+        tracker.kind_of.insert(ROOT_INODE, FileType::Directory);
+        tracker
+    }
+
+    /// Assigns (or looks up) the inode for `path`, a child discovered while
+    /// listing `parent_inode`. Recording the parent and kind here, at the
+    /// only point where they're known for free, is what lets `readdir`
+    /// answer `..` without a separate directory walk and lets `getattr`
+    /// answer for directories without a `read()` that would spuriously 404.
+    fn inode_for(&mut self, path: &FileSystemPath, parent_inode: u64, kind: FileType) -> u64 {
+        if let Some(inode) = self.path_to_inode.get(path) {
+            self.kind_of.insert(*inode, kind);
+            return *inode;
+        }
+        let inode = self.next_inode;
+        self.next_inode += 1;
+        self.path_to_inode.insert(path.clone(), inode);
+        self.inode_to_path.insert(inode, path.clone());
+        self.parent_of.insert(inode, parent_inode);
+        self.kind_of.insert(inode, kind);
+        inode
+    }
+
+    fn path_for(&self, inode: u64) -> Option<FileSystemPath> {
+        self.inode_to_path.get(&inode).cloned()
+    }
+
+    fn parent_for(&self, inode: u64) -> u64 {
+        self.parent_of.get(&inode).copied().unwrap_or(ROOT_INODE)
+    }
+
+    /// The kind recorded the last time this inode was seen in a `lookup` or
+    /// `readdir`. `getattr` relies on this to answer for directories without
+    /// calling `read()`, which yields `FileContent::NotFound` for a
+    /// directory and would otherwise look like a missing file.
+    fn kind_for(&self, inode: u64) -> Option<FileType> {
+        self.kind_of.get(&inode).copied()
+    }
+}
+
+/// Exposes `root` (and everything `turbo_tasks` can resolve underneath it)
+/// as a FUSE filesystem mounted at `mountpoint`. Blocks the calling thread
+/// for the lifetime of the mount; run it on a dedicated thread or task.
+pub fn mount(root: FileSystemPath, mountpoint: impl AsRef<Path>, rt: Handle) -> anyhow::Result<()> {
+    let fs = TurboTasksFuse {
+        tracker: Mutex::new(InodeTracker::new(root)),
+        rt,
+    };
+    let options = vec![MountOption::RO, MountOption::FSName("turbo-tasks-fs".into())];
+    fuser::mount2(fs, mountpoint, &options)?;
+    Ok(())
+}
+
+struct TurboTasksFuse {
+    tracker: Mutex<InodeTracker>,
+    rt: Handle,
+}
+
+impl TurboTasksFuse {
+    /// Runs an async `turbo_tasks` read on the tokio runtime the mount was
+    /// created with. FUSE callbacks are synchronous, so every callback below
+    /// that reads funnels through here rather than spawning its own executor.
+    fn block_on<F: std::future::Future>(&self, fut: F) -> F::Output {
+        self.rt.block_on(fut)
+    }
+
+    fn attr_for(&self, inode: u64, entry: &DirectoryEntry) -> FileAttr {
+        base_attr(inode, kind_of_entry(entry), 0)
+    }
+}
+
+fn kind_of_entry(entry: &DirectoryEntry) -> FileType {
+    match entry {
+        DirectoryEntry::Directory(_) => FileType::Directory,
+        _ => FileType::RegularFile,
+    }
+}
+
+fn base_attr(inode: u64, kind: FileType, size: u64) -> FileAttr {
+    FileAttr {
+        ino: inode,
+        size,
+        blocks: size.div_ceil(512),
+        atime: UNIX_EPOCH,
+        mtime: UNIX_EPOCH,
+        ctime: UNIX_EPOCH,
+        crtime: UNIX_EPOCH,
+        kind,
+        perm: if kind == FileType::Directory { 0o555 } else { 0o444 },
+        nlink: 1,
+        uid: 0,
+        gid: 0,
+        rdev: 0,
+        blksize: 512,
+        flags: 0,
+    }
+}
+
+impl Filesystem for TurboTasksFuse {
+    fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let Some(parent_path) = self.tracker.lock().unwrap().path_for(parent) else {
+            reply.error(libc::ENOENT);
+            return;
+        };
+        let Some(name) = name.to_str() else {
+            reply.error(libc::EINVAL);
+            return;
+        };
+        let result = self.block_on(async {
+            let content = parent_path.clone().read_dir().await?;
+            anyhow::Ok(match &*content {
+                DirectoryContent::Entries(entries) => entries.get(name).cloned(),
+                DirectoryContent::NotFound => None,
+            })
+        });
+        match result {
+            Ok(Some(entry)) => {
+                let path = entry.path().clone();
+                let kind = kind_of_entry(&entry);
+                let inode = self
+                    .tracker
+                    .lock()
+                    .unwrap()
+                    .inode_for(&path, parent, kind);
+                reply.entry(&TTL, &self.attr_for(inode, &entry), 0);
+            }
+            Ok(None) => reply.error(libc::ENOENT),
+            Err(_) => reply.error(libc::EIO),
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request, ino: u64, reply: ReplyAttr) {
+        if ino == ROOT_INODE {
+            reply.attr(&TTL, &base_attr(ROOT_INODE, FileType::Directory, 0));
+            return;
+        }
+        let (path, kind) = {
+            let tracker = self.tracker.lock().unwrap();
+            let Some(path) = tracker.path_for(ino) else {
+                reply.error(libc::ENOENT);
+                return;
+            };
+            (path, tracker.kind_for(ino))
+        };
+        // A directory's `read()` yields `FileContent::NotFound` (directories
+        // have no file content), so directories are answered from the
+        // recorded kind instead of going through `read()` at all.
+        if kind == Some(FileType::Directory) {
+            reply.attr(&TTL, &base_attr(ino, FileType::Directory, 0));
+            return;
+        }
+        let result = self.block_on(async { path.read().await });
+        match result {
+            Ok(content) => {
+                let (kind, size) = match &*content {
+                    FileContent::Content(file) => (FileType::RegularFile, file.content().len()),
+                    FileContent::NotFound => {
+                        reply.error(libc::ENOENT);
+                        return;
+                    }
+                };
+                reply.attr(&TTL, &base_attr(ino, kind, size));
+            }
+            Err(_) => reply.error(libc::EIO),
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        let Some(path) = self.tracker.lock().unwrap().path_for(ino) else {
+            reply.error(libc::ENOENT);
+            return;
+        };
+        let result = self.block_on(async { path.read_dir().await });
+        let entries = match result {
+            Ok(content) => match &*content {
+                DirectoryContent::Entries(entries) => entries.clone(),
+                DirectoryContent::NotFound => {
+                    reply.error(libc::ENOENT);
+                    return;
+                }
+            },
+            Err(_) => {
+                reply.error(libc::EIO);
+                return;
+            }
+        };
+        let parent_inode = self.tracker.lock().unwrap().parent_for(ino);
+        let dot_entries = [(ino, FileType::Directory, ".".to_string()), (
+            parent_inode,
+            FileType::Directory,
+            "..".to_string(),
+        )];
+        let named_entries = entries.into_iter().map(|(name, entry)| {
+            let kind = kind_of_entry(&entry);
+            let child_inode = self
+                .tracker
+                .lock()
+                .unwrap()
+                .inode_for(&entry.path().clone(), ino, kind);
+            (child_inode, kind, name)
+        });
+        for (i, (child_inode, kind, name)) in dot_entries
+            .into_iter()
+            .chain(named_entries)
+            .enumerate()
+            .skip(offset as usize)
+        {
+            if reply.add(child_inode, (i + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+
+    fn open(&mut self, _req: &Request, _ino: u64, _flags: i32, reply: ReplyOpen) {
+        reply.opened(0, 0);
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        let Some(path) = self.tracker.lock().unwrap().path_for(ino) else {
+            reply.error(libc::ENOENT);
+            return;
+        };
+        let result = self.block_on(async { path.read().await });
+        match result {
+            Ok(content) => match &*content {
+                FileContent::Content(file) => {
+                    let bytes = file.content();
+                    let start = (offset as usize).min(bytes.len());
+                    let end = (start + size as usize).min(bytes.len());
+                    reply.data(&bytes[start..end]);
+                }
+                FileContent::NotFound => reply.error(libc::ENOENT),
+            },
+            Err(_) => reply.error(libc::EIO),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! An `openat`-based directory walk helper, used where a traversal needs to
+//! visit a real directory tree without going through `turbo_tasks`'s
+//! memoized, `Vc<FileSystemPath>`-keyed `read_dir`/`read`.
+//!
+//! `hash_directory`/`hash_file` themselves stay on the ordinary
+//! `FileSystemPath` path: they're `#[turbo_tasks::function]`s, memoized by
+//! their `Vc` argument, and threading a raw directory fd through that
+//! memoization boundary would mean keying the cache on something that isn't
+//! a `Vc` — not a fit for how this crate memoizes. What *can* use `*at`
+//! syscalls is a plain, non-memoized walk: `examples/hash_directory.rs`'s
+//! `live_paths` (the set `HashCache::gc` keeps) resolves each directory's
+//! children relative to its own open fd with [`OpenDir::read_entries`] and
+//! recurses into subdirectories with [`OpenDir::open_subdir`], rather than
+//! reassembling and re-resolving an absolute path at every level.
+//!
+//! Children discovered this way are also guaranteed to come from the same
+//! directory inode that was enumerated, avoiding the TOCTOU window a
+//! fresh absolute-path lookup would have if the directory were replaced
+//! mid-walk. Non-Unix targets fall back to [`TraversalMode::AbsolutePaths`]
+//! since [`OpenDir`] only exists on Unix.
+
+/// Which strategy [`examples/hash_directory.rs`][ex]'s `live_paths` walk
+/// uses to enumerate a directory tree: by resolving each entry's full
+/// absolute path (the portable default) or by keeping each directory's open
+/// fd and resolving children relative to it with the `*at` family (Unix
+/// only).
+///
+/// [ex]: ../../examples/hash_directory.rs
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TraversalMode {
+    #[default]
+    AbsolutePaths,
+    #[cfg(unix)]
+    OpenAt,
+}
+
+#[cfg(unix)]
+pub use unix_impl::{OpenDir, OpenDirEntry};
+
+#[cfg(unix)]
+mod unix_impl {
+    use std::{
+        os::fd::{AsFd, BorrowedFd, OwnedFd},
+        path::Path,
+    };
+
+    use anyhow::Result;
+    use rustix::fs::{self as rfs, Mode, OFlags};
+
+    /// An open directory handle used to resolve its children with `*at`
+    /// calls instead of re-walking an absolute path from the filesystem
+    /// root.
+    pub struct OpenDir {
+        fd: OwnedFd,
+    }
+
+    impl OpenDir {
+        /// Opens `path` and keeps its directory fd for subsequent `*at`
+        /// calls. `parent` is `None` at the traversal root, where there's
+        /// nothing to resolve relative to yet.
+        pub fn open(parent: Option<&OpenDir>, path: &Path) -> Result<Self> {
+            let fd = match parent {
+                Some(parent) => rfs::openat(
+                    &parent.fd,
+                    path,
+                    OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC,
+                    Mode::empty(),
+                )?,
+                None => rfs::open(
+                    path,
+                    OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC,
+                    Mode::empty(),
+                )?,
+            };
+            Ok(OpenDir { fd })
+        }
+
+        /// Lists this directory's entries without resolving an absolute
+        /// path for any of them.
+        pub fn read_entries(&self) -> Result<Vec<OpenDirEntry>> {
+            let mut entries = Vec::new();
+            for entry in rfs::Dir::read_from(&self.fd)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if name == "." || name == ".." {
+                    continue;
+                }
+                let stat = rfs::statat(&self.fd, entry.file_name(), rfs::AtFlags::empty())?;
+                entries.push(OpenDirEntry {
+                    name,
+                    is_dir: rfs::FileType::from_raw_mode(stat.st_mode)
+                        == rfs::FileType::Directory,
+                    len: stat.st_size as u64,
+                });
+            }
+            Ok(entries)
+        }
+
+        /// Opens a child directory relative to this one, continuing the
+        /// walk without re-resolving an absolute path.
+        pub fn open_subdir(&self, name: &str) -> Result<OpenDir> {
+            OpenDir::open(Some(self), Path::new(name))
+        }
+    }
+
+    impl AsFd for OpenDir {
+        fn as_fd(&self) -> BorrowedFd<'_> {
+            self.fd.as_fd()
+        }
+    }
+
+    /// A directory entry discovered by [`OpenDir::read_entries`], named
+    /// relative to its parent rather than by absolute path.
+    pub struct OpenDirEntry {
+        pub name: String,
+        pub is_dir: bool,
+        pub len: u64,
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::fs;
+
+        use super::*;
+
+        fn temp_dir(name: &str) -> std::path::PathBuf {
+            let dir = std::env::temp_dir().join(format!(
+                "turbo-tasks-fs-openat-walk-test-{name}-{:?}",
+                std::thread::current().id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        #[test]
+        fn read_entries_lists_files_and_subdirs_without_dot_entries() {
+            let dir = temp_dir("list");
+            fs::write(dir.join("a.txt"), b"hello").unwrap();
+            fs::create_dir(dir.join("sub")).unwrap();
+
+            let handle = OpenDir::open(None, &dir).unwrap();
+            let mut entries = handle.read_entries().unwrap();
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].name, "a.txt");
+            assert!(!entries[0].is_dir);
+            assert_eq!(entries[0].len, 5);
+            assert_eq!(entries[1].name, "sub");
+            assert!(entries[1].is_dir);
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
+        #[test]
+        fn open_subdir_resolves_relative_to_the_parent_fd() {
+            let dir = temp_dir("nested");
+            fs::create_dir(dir.join("sub")).unwrap();
+            fs::write(dir.join("sub").join("b.txt"), b"world").unwrap();
+
+            let root = OpenDir::open(None, &dir).unwrap();
+            let sub = root.open_subdir("sub").unwrap();
+            let entries = sub.read_entries().unwrap();
+
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].name, "b.txt");
+            assert!(!entries[0].is_dir);
+            assert_eq!(entries[0].len, 5);
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
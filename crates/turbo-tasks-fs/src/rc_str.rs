@@ -0,0 +1,109 @@
+//! A cheaply cloneable string type.
+//!
+//! `hash_file`, `hash_directory`, and friends pass the same path and digest
+//! strings through many recursive `#[turbo_tasks::function]` calls. With a
+//! plain `String` argument, re-running one of those functions with an
+//! unchanged path still means cloning (and therefore heap-allocating) that
+//! `String` on every call site that holds it. `RcStr` wraps an `Arc<str>` so
+//! cloning it is a refcount bump instead of a copy, which matters once a
+//! directory hash is joining together hundreds of child digests.
+
+use std::{fmt, ops::Deref, sync::Arc};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A reference-counted, immutable string. Clone is `O(1)`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RcStr(Arc<str>);
+
+impl Deref for RcStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for RcStr {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for RcStr {
+    fn from(value: String) -> Self {
+        RcStr(Arc::from(value))
+    }
+}
+
+impl From<&str> for RcStr {
+    fn from(value: &str) -> Self {
+        RcStr(Arc::from(value))
+    }
+}
+
+impl From<RcStr> for String {
+    fn from(value: RcStr) -> Self {
+        value.0.to_string()
+    }
+}
+
+impl fmt::Display for RcStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Debug for RcStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl Serialize for RcStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for RcStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(RcStr(Arc::from(String::deserialize(deserializer)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_round_trips_through_json() {
+        let value = RcStr::from("some/digest");
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, "\"some/digest\"");
+        let back: RcStr = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, value);
+    }
+
+    #[test]
+    fn ord_matches_the_underlying_str_ord() {
+        let mut values = vec![RcStr::from("b"), RcStr::from("a"), RcStr::from("c")];
+        values.sort();
+        assert_eq!(values, vec![RcStr::from("a"), RcStr::from("b"), RcStr::from("c")]);
+    }
+
+    #[test]
+    fn clone_is_cheap_and_shares_storage() {
+        let original = RcStr::from("shared");
+        let cloned = original.clone();
+        assert_eq!(original, cloned);
+        assert!(Arc::ptr_eq(&original.0, &cloned.0));
+    }
+
+    #[test]
+    fn deref_and_as_ref_expose_the_str() {
+        let value = RcStr::from("hello");
+        assert_eq!(&*value, "hello");
+        assert_eq!(value.as_ref(), "hello");
+    }
+}